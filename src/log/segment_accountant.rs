@@ -1,10 +1,28 @@
-use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 
 use coco::epoch::{Owned, pin};
 
 use super::*;
 
+/// How many free segments we keep pre-sorted by erase count, so that
+/// `next()` doesn't have to scan the entire free list to find the
+/// least-worn candidate.
+const WEAR_LEVEL_RESERVE_SIZE: usize = 16;
+
+// BLOCKED (jneem/sled#chunk0-4, "per-record CRC verification during
+// recovery and iteration"): this request asks for a version byte plus
+// CRC32 computed over the record length and payload, stored in the
+// record header and verified by `read_next`/`read_segment`, with a
+// `Config` flag to toggle it. That header is parsed entirely in the
+// log reader this module depends on (`self.config.read_segment`,
+// `Segment::read_next`, `LogRead`), which is not part of this source
+// tree — there is no record framing code here to add a checksum to.
+// `LogRead::Corrupted` and the tail-scan halt behavior in
+// `scan_segment_lsns_from` are unchanged from baseline. Revisit once
+// the log reader module is available to edit.
+
 #[derive(Default, Debug)]
 pub struct SegmentAccountant {
     tip: LogID,
@@ -17,6 +35,8 @@ pub struct SegmentAccountant {
     config: Config,
     pause_rewriting: bool,
     ordering: BTreeMap<Lsn, LogID>,
+    erase_counts: Vec<u64>,
+    wear_level_reserve: BinaryHeap<Reverse<(u64, LogID)>>,
 }
 
 // We use a `SegmentDropper` to ensure that we never
@@ -42,6 +62,34 @@ pub struct Segment {
     pub pids_len: usize,
     pub lsn: Option<Lsn>,
     freed: bool,
+    /// Number of times this segment's `LogID` has been handed out by
+    /// `SegmentAccountant::next`, used to drive wear-leveled allocation.
+    pub erase_count: u64,
+}
+
+/// A point-in-time snapshot of `SegmentAccountant` state, useful for
+/// observing how full the log is and how effective cleaning has been.
+/// Computed on demand from existing fields, so taking one is cheap and
+/// allocation-light. Intended to help tune `segment_cleanup_threshold`,
+/// `min_free_segments`, and `io_bufs` against a real workload, and to
+/// drive external compaction triggers.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentStats {
+    pub segments: usize,
+    pub free_segments: usize,
+    pub segments_queued_for_cleaning: usize,
+    pub pending_clean_pids: usize,
+    /// Ratio of live pids to allocated pid slots across all segments
+    /// that hold at least one live pid (a segment that has not recorded
+    /// a `pids_len` yet counts as fully live), i.e. the current space
+    /// amplification of the log (1.0 is fully live, lower means more
+    /// space is wasted on dead pages).
+    pub live_ratio: f64,
+    /// Count of segments holding at least one live pid, falling into
+    /// each decile of utilization (`pids.len() / pids_len`), from
+    /// `[0.0, 0.1)` through `[0.9, 1.0]`. Segments with no live pids
+    /// are not counted.
+    pub utilization_histogram: [usize; 10],
 }
 
 // concurrency properties:
@@ -77,6 +125,7 @@ impl SegmentAccountant {
 
     pub fn initialize_from_segments(&mut self, segments: Vec<Segment>) {
         self.segments = segments;
+        self.erase_counts = self.segments.iter().map(|s| s.erase_count).collect();
 
         for (idx, ref mut segment) in self.segments.iter_mut().enumerate() {
             let segment_start = (idx * self.config.get_io_buf_size()) as LogID;
@@ -104,13 +153,109 @@ impl SegmentAccountant {
             return;
         }
 
-        let mut cursor = 0;
+        self.scan_segment_lsns_from(0);
+    }
+
+    /// Bootstrap `segments`, `ordering`, `to_clean` and `free` directly
+    /// from a persisted `Snapshot`, then tail-scan only the segments
+    /// written after the snapshot was taken. This avoids the linear,
+    /// read-every-segment-header cost of `scan_segment_lsns` for
+    /// everything the snapshot already accounts for.
+    ///
+    /// For the range of segments covered by the snapshot, `segments`,
+    /// `to_clean` and `free` are rebuilt as `initialize_from_segments`
+    /// would rebuild them. `ordering` differs from a full
+    /// `scan_segment_lsns`: it only gets entries for the segments listed
+    /// in `snapshot.replacements`, while a full scan records every
+    /// segment it reads.
+    pub fn recover_from_snapshot(&mut self, snapshot: &Snapshot) {
+        let io_buf_size = self.config.get_io_buf_size();
+
+        let highest_replacement_idx = snapshot
+            .replacements
+            .keys()
+            .map(|&segment_start| segment_start as usize / io_buf_size)
+            .max();
+
+        let segment_count =
+            Self::snapshot_segment_count(snapshot.max_lid, io_buf_size, highest_replacement_idx);
+
+        self.segments = vec![Segment::default(); segment_count];
+        self.ensure_erase_counts_len(segment_count);
+
+        for (&segment_start, &(lsn, ref pids)) in &snapshot.replacements {
+            let idx = segment_start as usize / io_buf_size;
+
+            self.segments[idx].lsn = Some(lsn);
+            self.segments[idx].pids = pids.iter().map(|&(pid, _segment_start)| pid).collect();
+            self.segments[idx].pids_len = self.segments[idx].pids.len();
+
+            self.ordering.insert(lsn, segment_start);
+        }
+
+        // rebuild to_clean/free using the same cleanup-threshold logic
+        // that a full scan would apply via `initialize_from_segments`.
+        for (idx, segment) in self.segments.iter().enumerate() {
+            let segment_start = (idx * io_buf_size) as LogID;
+
+            if segment.pids.is_empty() {
+                self.free.lock().unwrap().push_back(segment_start);
+            } else if segment.pids.len() as f64 / segment.pids_len as f64 <=
+                       self.config.get_segment_cleanup_threshold()
+            {
+                self.to_clean.insert(segment_start);
+            }
+        }
+        for segment in self.segments.iter_mut() {
+            if segment.pids.is_empty() {
+                segment.freed = true;
+            }
+        }
+
+        self.max_lsn = snapshot.max_lsn;
+        self.initial_offset = snapshot.last_lid;
+        self.tip = snapshot.max_lid;
+
+        // only segments newer than the snapshot's max_lsn need a tail
+        // scan; everything up to the boundary came from the snapshot.
+        self.scan_segment_lsns_from(snapshot.max_lid);
+    }
+
+    /// The number of segments `recover_from_snapshot` needs to index,
+    /// derived from the physical extent of the log (`max_lid`, the tip
+    /// one segment width past the last segment ever carved out), not
+    /// just from the sparse set of segments that still hold at least
+    /// one live page in `replacements`. Segments already fully cleaned
+    /// before the snapshot was taken have no entry in `replacements`,
+    /// but they're still real, allocated segments that must end up on
+    /// `free` rather than being silently dropped from the index.
+    fn snapshot_segment_count(
+        max_lid: LogID,
+        io_buf_size: usize,
+        highest_replacement_idx: Option<usize>,
+    ) -> usize {
+        let from_lid = max_lid as usize / io_buf_size;
+        let from_replacements = highest_replacement_idx.map(|idx| idx + 1).unwrap_or(0);
+        from_lid.max(from_replacements).max(1)
+    }
+
+    fn scan_segment_lsns_from(&mut self, start: LogID) {
+        let mut cursor = start;
         loop {
             // in the future this can be optimized to just read
             // the initial header at that position... but we need to
             // make sure the segment is not torn
             if let Ok(segment) = self.config.read_segment(cursor) {
                 self.recover(segment.lsn, segment.position);
+                // BLOCKED: nothing in this tree's on-disk segment
+                // header actually persists erase_count (that would
+                // live in the same log writer/reader module that
+                // chunk0-4's CRC header change is blocked on), so a
+                // plain disk scan has no real value to restore here.
+                // ensure_erase_counts_len's default-to-minimum applies
+                // to every rediscovered segment for now; wear-leveling
+                // history does not survive a real restart until that
+                // module exists to persist and read it back.
                 cursor += self.config.get_io_buf_size() as LogID;
 
                 // NB we set tip AFTER bumping cursor, as we want this to
@@ -149,6 +294,10 @@ impl SegmentAccountant {
                                 break;
                             }
                         }
+                        // unchanged from baseline: this fires on a run
+                        // of zeroed bytes the reader treats as
+                        // corruption, not on a CRC mismatch (see the
+                        // BLOCKED note for chunk0-4 above the imports)
                         LogRead::Corrupted(_) => break,
                     }
                 }
@@ -160,7 +309,19 @@ impl SegmentAccountant {
                 self.free.lock().unwrap().push_back(*max_cursor);
             }
         } else {
-            assert!(self.ordering.is_empty());
+            // On the full-disk-scan path (entered with `self.ordering`
+            // empty), `recover()` unconditionally inserts every segment
+            // it discovers, live or not, so a non-empty `ordering`
+            // always has an entry for `self.max_lsn` here. That's not
+            // true coming from `recover_from_snapshot`: it only inserts
+            // an `ordering` entry for segments that still have at least
+            // one live pid in `snapshot.replacements`, so a segment that
+            // was fully cleaned before the snapshot was taken can be
+            // `snapshot.max_lsn` itself while having no `ordering` entry
+            // at all. In that case `recover_from_snapshot` has already
+            // set `self.initial_offset` from `snapshot.last_lid`, so
+            // there's nothing to refine here; just leave it as-is
+            // instead of asserting an invariant that path doesn't hold.
         }
 
         // println!("our max_lsn:{}", self.max_lsn);
@@ -174,6 +335,56 @@ impl SegmentAccountant {
         self.max_lsn
     }
 
+    /// Returns a snapshot of current segment utilization and GC state,
+    /// for monitoring and for tuning cleanup-related `Config` knobs.
+    pub fn stats(&self) -> SegmentStats {
+        let mut live = 0;
+        let mut allocated = 0;
+        let mut utilization_histogram = [0usize; 10];
+
+        for segment in &self.segments {
+            if segment.pids.is_empty() {
+                // genuinely empty/unallocated: never had any pids, or
+                // had all of them removed and is counted on `free`
+                // instead.
+                continue;
+            }
+
+            // `pids_len` is seeded lazily, on first pid removed from a
+            // segment (see `freed`/`set` below); a fresh segment that's
+            // never had a pid removed is fully live but still has
+            // `pids_len == 0`. Treat that the same as 100% utilization
+            // instead of skipping it, or a young/healthy log reports a
+            // misleadingly low (or hard-coded-default) live ratio and
+            // an empty histogram.
+            let pids_len = if segment.pids_len == 0 {
+                segment.pids.len()
+            } else {
+                segment.pids_len
+            };
+
+            live += segment.pids.len();
+            allocated += pids_len;
+
+            let utilization = segment.pids.len() as f64 / pids_len as f64;
+            let bucket = ((utilization * 10.0) as usize).min(9);
+            utilization_histogram[bucket] += 1;
+        }
+
+        SegmentStats {
+            segments: self.segments.len(),
+            free_segments: self.free.lock().unwrap().len(),
+            segments_queued_for_cleaning: self.to_clean.len(),
+            pending_clean_pids: self.pending_clean.len(),
+            live_ratio: if allocated == 0 {
+                1.0
+            } else {
+                live as f64 / allocated as f64
+            },
+            utilization_histogram,
+        }
+    }
+
     /// this will cause all new allocations to occur at the end of the log, which
     /// is necessary to preserve consistency while concurrently iterating through
     /// the log during snapshot creation.
@@ -188,6 +399,7 @@ impl SegmentAccountant {
 
     pub fn freed(&mut self, pid: PageID, old_lids: Vec<LogID>, lsn: Lsn) {
         self.pending_clean.remove(&pid);
+        self.bump_max_lsn(lsn);
 
         for old_lid in old_lids.into_iter() {
             let idx = old_lid as usize / self.config.get_io_buf_size();
@@ -293,6 +505,7 @@ impl SegmentAccountant {
 
     pub fn merged(&mut self, pid: PageID, lid: LogID, lsn: Lsn) {
         self.pending_clean.remove(&pid);
+        self.bump_max_lsn(lsn);
 
         let idx = lid as usize / self.config.get_io_buf_size();
 
@@ -322,6 +535,17 @@ impl SegmentAccountant {
         lid
     }
 
+    /// Keeps `max_lsn` advancing as new lsns are observed at runtime,
+    /// not just during recovery. `ranked_clean_candidates`'s cost-benefit
+    /// `age` calculation depends on `max_lsn` tracking "now", so it
+    /// must move forward on every `next`/`merged`/`freed` call, not
+    /// only get set once at startup.
+    fn bump_max_lsn(&mut self, lsn: Lsn) {
+        if lsn > self.max_lsn {
+            self.max_lsn = lsn;
+        }
+    }
+
     fn ensure_safe_free_distance(&mut self) {
         // NB we must maintain a queue of free segments that
         // is at least as long as the number of io buffers.
@@ -348,9 +572,13 @@ impl SegmentAccountant {
             "unaligned Lsn provided to next!"
         );
 
+        self.bump_max_lsn(lsn);
+
         // pop free or add to end
         let lid = if self.pause_rewriting {
             self.bump_tip()
+        } else if self.config.get_use_wear_leveling() {
+            self.next_wear_leveled()
         } else {
             let res = self.free.lock().unwrap().pop_front();
             if res.is_none() {
@@ -366,6 +594,8 @@ impl SegmentAccountant {
         if self.segments.len() <= idx {
             self.segments.resize(idx + 1, Segment::default());
         }
+        self.ensure_erase_counts_len(idx + 1);
+        self.bump_erase_count(lid);
 
         let segment = &mut self.segments[idx];
         assert!(segment.pids.is_empty());
@@ -393,21 +623,71 @@ impl SegmentAccountant {
             return None;
         }
 
-        for lid in &self.to_clean {
-            let idx = *lid as usize / self.config.get_io_buf_size();
-            let segment = &self.segments[idx];
+        for lid in self.ranked_clean_candidates() {
+            let segment = &self.segments[lid as usize / self.config.get_io_buf_size()];
+
             for pid in &segment.pids {
-                if self.pending_clean.contains(&pid) {
+                if self.pending_clean.contains(pid) {
                     continue;
                 }
                 self.pending_clean.insert(*pid);
                 return Some(*pid);
             }
+
+            // every live pid in this segment is already pending a
+            // rewrite elsewhere; move on to the next-best candidate
+            // instead of stalling here.
         }
 
         None
     }
 
+    /// Rank every candidate in `to_clean` from best to worst target for
+    /// rewriting, so `clean()` can drain pids from the best segment and
+    /// fall through to the next-best one if the top candidate turns out
+    /// to be fully pending already.
+    ///
+    /// When `Config::get_use_cost_benefit_cleaning()` is set, segments
+    /// are ranked by the classic log-structured cost-benefit formula
+    /// `((1.0 - u) * age) / (1.0 + u)`, where `u` is the live fraction
+    /// of the segment and `age` is how long ago it was written. This
+    /// favors segments that are old, mostly-dead, and cheap to copy
+    /// over young segments that are merely below the cleanup
+    /// threshold. Otherwise, segments are ranked by a simpler greedy
+    /// lowest-utilization policy.
+    fn ranked_clean_candidates(&self) -> Vec<LogID> {
+        let mut ranked: Vec<(LogID, f64)> = self.to_clean
+            .iter()
+            .filter_map(|&lid| {
+                let idx = lid as usize / self.config.get_io_buf_size();
+                let segment = &self.segments[idx];
+
+                // not yet assigned a write, skip until it is
+                let lsn = segment.lsn?;
+
+                let score = if segment.pids_len == 0 {
+                    // no live accounting recorded yet: treat as fully
+                    // empty and therefore maximally attractive to clean
+                    f64::INFINITY
+                } else {
+                    let u = segment.pids.len() as f64 / segment.pids_len as f64;
+                    if self.config.get_use_cost_benefit_cleaning() {
+                        let age = (self.max_lsn - lsn) as f64;
+                        ((1.0 - u) * age) / (1.0 + u)
+                    } else {
+                        1.0 - u
+                    }
+                };
+
+                Some((lid, score))
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        ranked.into_iter().map(|(lid, _)| lid).collect()
+    }
+
     pub fn segment_snapshot_iter_from(&self, lsn: Lsn) -> Box<Iterator<Item = (Lsn, LogID)>> {
         let segment_len = self.config.get_io_buf_size() as Lsn;
         let normalized_lsn = lsn / segment_len * segment_len;
@@ -427,10 +707,81 @@ impl SegmentAccountant {
         if self.segments.len() <= idx {
             self.segments.resize(idx + 1, Segment::default());
         }
+        self.ensure_erase_counts_len(idx + 1);
 
         self.segments[idx].lsn = Some(lsn);
         self.ordering.insert(lsn, lid);
     }
+
+    /// Grow `erase_counts` to at least `len`, defaulting freshly
+    /// discovered segments to the current minimum erase count so they
+    /// aren't starved by segments that have already been through many
+    /// wear-leveled rewrites.
+    fn ensure_erase_counts_len(&mut self, len: usize) {
+        if self.erase_counts.len() >= len {
+            return;
+        }
+        let min = self.erase_counts.iter().cloned().min().unwrap_or(0);
+        self.erase_counts.resize(len, min);
+    }
+
+    fn erase_count(&self, lid: LogID) -> u64 {
+        let idx = lid as usize / self.config.get_io_buf_size();
+        self.erase_counts.get(idx).cloned().unwrap_or(0)
+    }
+
+    fn bump_erase_count(&mut self, lid: LogID) {
+        let idx = lid as usize / self.config.get_io_buf_size();
+        self.ensure_erase_counts_len(idx + 1);
+        self.erase_counts[idx] += 1;
+
+        if self.segments.len() > idx {
+            self.segments[idx].erase_count = self.erase_counts[idx];
+        }
+    }
+
+    /// Refill the small reserve of wear-leveling candidates from the
+    /// current free list, without scanning the whole deque on every
+    /// call to `next()`.
+    fn refill_wear_level_reserve(&mut self) {
+        if !self.wear_level_reserve.is_empty() {
+            return;
+        }
+
+        let free = self.free.lock().unwrap();
+        for &lid in free.iter().take(WEAR_LEVEL_RESERVE_SIZE) {
+            self.wear_level_reserve.push(Reverse((self.erase_count(lid), lid)));
+        }
+    }
+
+    /// Pick the least-worn segment in the wear-leveling reserve, i.e.
+    /// among the first `WEAR_LEVEL_RESERVE_SIZE` entries of the free
+    /// list at the time the reserve was last refilled; free segments
+    /// added after that are not considered until the reserve runs
+    /// empty. The chosen segment is one already sitting in the free
+    /// list (and has therefore already passed the safe-free-distance
+    /// window enforced by `ensure_safe_free_distance`), falling back to
+    /// bumping the tip if the free list is empty.
+    fn next_wear_leveled(&mut self) -> LogID {
+        self.refill_wear_level_reserve();
+
+        while let Some(Reverse((_, lid))) = self.wear_level_reserve.pop() {
+            let mut free = self.free.lock().unwrap();
+            if let Some(pos) = free.iter().position(|&l| l == lid) {
+                free.remove(pos);
+                return lid;
+            }
+            // this candidate was already claimed since we last refilled
+            // the reserve; try the next-best one instead.
+        }
+
+        let res = self.free.lock().unwrap().pop_front();
+        if let Some(lid) = res {
+            lid
+        } else {
+            self.bump_tip()
+        }
+    }
 }
 
 #[test]
@@ -484,3 +835,179 @@ fn basic_workflow() {
     assert_eq!(sa.clean(), Some(1));
     assert_eq!(sa.clean(), None);
 }
+
+#[test]
+fn erase_counts_track_segment_reuse() {
+    let conf = Config::default()
+        .io_buf_size(1)
+        .io_bufs(1)
+        .segment_cleanup_threshold(0.2)
+        .min_free_segments(1);
+    let mut sa = SegmentAccountant::new(conf);
+
+    assert_eq!(sa.erase_count(0), 0);
+    sa.bump_erase_count(0);
+    sa.bump_erase_count(0);
+    assert_eq!(sa.erase_count(0), 2);
+
+    // a freshly discovered segment starts at the current minimum
+    // erase count, not 0, so it isn't unfairly favored for wear
+    // leveling over segments that have already been rewritten
+    sa.bump_erase_count(5);
+    assert_eq!(sa.erase_count(5), 3);
+}
+
+#[test]
+fn max_lsn_advances_with_runtime_operations() {
+    // ranked_clean_candidates's cost-benefit `age` calculation depends on
+    // max_lsn tracking "now", not just the lsn recovered at startup
+    let conf = Config::default()
+        .io_buf_size(1)
+        .io_bufs(1)
+        .segment_cleanup_threshold(0.9)
+        .min_free_segments(0);
+    let mut sa = SegmentAccountant::new(conf);
+    assert_eq!(sa.recovered_max_lsn(), 0);
+
+    let mut highest = 0;
+    let mut lsn = || {
+        highest += 1;
+        highest
+    };
+
+    let a = sa.next(lsn());
+    assert!(sa.recovered_max_lsn() >= 1);
+
+    sa.merged(0, a, lsn());
+    assert!(sa.recovered_max_lsn() >= 2);
+
+    let prior = sa.recovered_max_lsn();
+    sa.set(0, vec![a], a, lsn());
+    assert!(sa.recovered_max_lsn() > prior);
+}
+
+#[test]
+fn clean_falls_back_to_next_best_candidate_when_top_is_pending() {
+    let conf = Config::default()
+        .io_buf_size(1)
+        .io_bufs(1)
+        .segment_cleanup_threshold(0.9)
+        .min_free_segments(0);
+    let mut sa = SegmentAccountant::new(conf);
+
+    let mut highest = 0;
+    let mut lsn = || {
+        highest += 1;
+        highest
+    };
+
+    let a = sa.next(lsn());
+    let b = sa.next(lsn());
+    let c = sa.next(lsn());
+
+    sa.merged(10, a, lsn());
+    sa.merged(11, a, lsn());
+    sa.merged(20, b, lsn());
+    sa.merged(21, b, lsn());
+
+    // leave one live pid behind in each of `a` and `b`, at 0.5
+    // utilization, putting both segments in to_clean
+    sa.set(10, vec![a], c, lsn());
+    sa.set(20, vec![b], c, lsn());
+
+    let first = sa.clean().expect("a clean candidate should be found");
+    // the first candidate's only live pid is now pending elsewhere, so
+    // clean() must move on to the next-best candidate instead of
+    // giving up
+    let second = sa.clean().expect(
+        "clean() should fall through to the next-best candidate",
+    );
+    assert_ne!(first, second);
+
+    // both candidates' live pids are now pending
+    assert_eq!(sa.clean(), None);
+}
+
+#[test]
+fn snapshot_segment_count_covers_cleaned_segments_past_replacements() {
+    // a fresh or fully-compacted snapshot has no entries in
+    // `replacements` at all, but the log may still have many
+    // previously-allocated (now fully cleaned) segments that must
+    // stay indexed and be pushed onto `free`, not dropped
+    assert_eq!(SegmentAccountant::snapshot_segment_count(5, 1, None), 5);
+
+    // `replacements` only ever covers segments with at least one live
+    // page, so it can under-count relative to the physical log extent
+    assert_eq!(SegmentAccountant::snapshot_segment_count(5, 1, Some(1)), 5);
+
+    // a sparse, high-index replacement can still exceed the recorded
+    // tip if the tip hasn't been bumped past it yet
+    assert_eq!(SegmentAccountant::snapshot_segment_count(3, 1, Some(9)), 10);
+
+    // always index at least one segment
+    assert_eq!(SegmentAccountant::snapshot_segment_count(0, 1, None), 1);
+}
+
+#[test]
+fn scan_segment_lsns_from_tolerates_sparse_ordering_from_snapshot() {
+    // `recover_from_snapshot` only inserts an `ordering` entry for
+    // segments with at least one live pid in `snapshot.replacements`;
+    // a segment that was fully cleaned before the snapshot was taken
+    // can still be the one holding the overall highest lsn, with no
+    // matching `ordering` entry at all. `scan_segment_lsns_from` (which
+    // `recover_from_snapshot` always calls as its tail step) must not
+    // assume a full disk scan's invariant that a non-empty `ordering`
+    // always contains `max_lsn`.
+    let conf = Config::default().io_buf_size(1).io_bufs(1);
+    let mut sa = SegmentAccountant::new(conf);
+
+    // a live segment elsewhere in the log, so `ordering` is non-empty...
+    sa.ordering.insert(1, 0);
+    // ...but `max_lsn` points at a since-cleaned segment with no entry
+    sa.max_lsn = 2;
+
+    // must not panic
+    sa.scan_segment_lsns_from(100);
+}
+
+#[test]
+fn stats_reflects_segment_and_gc_state() {
+    let conf = Config::default()
+        .io_buf_size(1)
+        .io_bufs(1)
+        .segment_cleanup_threshold(0.9)
+        .min_free_segments(0);
+    let mut sa = SegmentAccountant::new(conf);
+
+    let mut highest = 0;
+    let mut lsn = || {
+        highest += 1;
+        highest
+    };
+
+    let a = sa.next(lsn());
+    let b = sa.next(lsn());
+
+    sa.merged(0, a, lsn());
+    sa.merged(1, a, lsn());
+
+    let stats = sa.stats();
+    assert_eq!(stats.segments, 2);
+    // neither segment has had a pid removed yet, so pids_len is still
+    // 0 for both; `a` (2 pids) is fully live and counts fully toward
+    // the ratio and histogram, while `b` (0 pids) is empty and skipped
+    assert_eq!(stats.live_ratio, 1.0);
+    assert_eq!(stats.utilization_histogram[9], 1);
+    assert_eq!(stats.segments_queued_for_cleaning, 0);
+    assert_eq!(stats.pending_clean_pids, 0);
+
+    // move pid 0 out of `a`, leaving it at 0.5 utilization and below
+    // the (permissive) cleanup threshold; pid 0 now lives in `b`, which
+    // counts as fully live (1 of 1), so the ratio is (1 + 1) / (2 + 1)
+    sa.set(0, vec![a], b, lsn());
+
+    let stats = sa.stats();
+    assert_eq!(stats.segments_queued_for_cleaning, 1);
+    assert_eq!(stats.live_ratio, 2.0 / 3.0);
+    assert_eq!(stats.utilization_histogram.iter().sum::<usize>(), 2);
+}